@@ -0,0 +1,174 @@
+//! Disk-backed sharded index builder.
+//!
+//! Building a large index with `Bigsi::insert` requires the full `Vec<BitVec>`
+//! matrix in memory. `BigsiBuilder` partitions the bucket range `0..m` into
+//! `num_shards` contiguous shards and buffers inserts per shard. All shard
+//! buffers are allocated when the builder is created. `flush_shard` hands a
+//! shard's buffer to a dedicated writer thread (a bounded channel hand-off,
+//! so hashing and insertion on the calling thread isn't blocked on I/O),
+//! which writes it to disk, and frees it; shards are not flushed otherwise
+//! until `finish`. `finish` flushes the remaining shards, then reads the
+//! shard files back and concatenates them into an in-memory [`Bigsi`], so
+//! the finished index must fit in RAM.
+
+use crate::{Bigsi, HashKind};
+use bincode::{deserialize_from, serialize_into};
+use bv::BitVec;
+use bv::*;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread::JoinHandle;
+
+enum ShardMessage {
+    Flush { shard: usize, rows: Vec<BitVec> },
+    Stop,
+}
+
+/// Builds a [`Bigsi`] index shard by shard, flushing completed shards to disk
+/// on a background writer thread. Inserts route to a shard by `bit_index / shard_size`.
+pub struct BigsiBuilder {
+    m: usize,
+    n: u64,
+    eta: u64,
+    hash_kind: HashKind,
+    shard_size: usize,
+    num_shards: usize,
+    dir: PathBuf,
+    shards: Vec<Option<Vec<BitVec>>>,
+    sender: SyncSender<ShardMessage>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl BigsiBuilder {
+    /// Create a builder for an index of size `m` with `n` accessions and `eta`
+    /// hashes, split into `num_shards` contiguous shards written under `dir`.
+    /// Hashes with the default backend ([`HashKind::XxHash`]).
+    pub fn new(m: usize, n: u64, eta: u64, num_shards: usize, dir: &str) -> BigsiBuilder {
+        BigsiBuilder::new_with_hash(m, n, eta, num_shards, dir, HashKind::default())
+    }
+
+    /// Create a builder like [`BigsiBuilder::new`], hashing with `hash_kind`.
+    pub fn new_with_hash(
+        m: usize,
+        n: u64,
+        eta: u64,
+        num_shards: usize,
+        dir: &str,
+        hash_kind: HashKind,
+    ) -> BigsiBuilder {
+        std::fs::create_dir_all(dir).unwrap();
+        let shard_size = (m + num_shards - 1) / num_shards;
+        let shards = (0..num_shards)
+            .map(|i| {
+                let len = shard_size.min(m.saturating_sub(i * shard_size));
+                Some(vec![BitVec::new_fill(false, n); len])
+            })
+            .collect();
+
+        let writer_dir = PathBuf::from(dir);
+        let (sender, receiver) = sync_channel::<ShardMessage>(2);
+        let writer = std::thread::spawn(move || loop {
+            match receiver.recv() {
+                Ok(ShardMessage::Flush { shard, rows }) => {
+                    let path = writer_dir.join(format!("shard_{}.bxi", shard));
+                    let mut w = BufWriter::new(File::create(path).unwrap());
+                    serialize_into(&mut w, &rows).unwrap();
+                }
+                Ok(ShardMessage::Stop) | Err(_) => break,
+            }
+        });
+
+        BigsiBuilder {
+            m,
+            n,
+            eta,
+            hash_kind,
+            shard_size,
+            num_shards,
+            dir: PathBuf::from(dir),
+            shards,
+            sender,
+            writer: Some(writer),
+        }
+    }
+
+    fn shard_for(&self, bit_index: usize) -> (usize, usize) {
+        (bit_index / self.shard_size, bit_index % self.shard_size)
+    }
+
+    /// Insert `value` for `accession`, routing each hash to its shard buffer.
+    pub fn insert(&mut self, accession: u64, value: &str) {
+        for i in 0..self.eta {
+            let bit_index = (self.hash_kind.hash_with_seed(&value.as_bytes(), i) % self.m as u64)
+                as usize;
+            let (shard, local) = self.shard_for(bit_index);
+            self.shards[shard]
+                .as_mut()
+                .expect("shard already flushed")[local]
+                .set(accession, true);
+        }
+    }
+
+    /// Flush a shard to disk now on the background writer thread, freeing its
+    /// in-memory buffer. `insert` must not target this shard afterwards.
+    pub fn flush_shard(&mut self, shard: usize) {
+        if let Some(rows) = self.shards[shard].take() {
+            self.sender
+                .send(ShardMessage::Flush { shard, rows })
+                .expect("writer thread gone");
+        }
+    }
+
+    /// Flush any remaining shards, join the writer thread, and concatenate
+    /// the shard files into a single `Bigsi` index.
+    pub fn finish(mut self) -> Bigsi {
+        for shard in 0..self.num_shards {
+            self.flush_shard(shard);
+        }
+        self.sender.send(ShardMessage::Stop).ok();
+        self.writer.take().unwrap().join().unwrap();
+
+        let mut bigsi = Vec::with_capacity(self.m);
+        for shard in 0..self.num_shards {
+            let path = self.dir.join(format!("shard_{}.bxi", shard));
+            let reader = BufReader::new(File::open(path).unwrap());
+            let rows: Vec<BitVec> = deserialize_from(reader).unwrap();
+            bigsi.extend(rows);
+        }
+
+        Bigsi {
+            bigsi,
+            num_hashes: self.eta,
+            accessions: self.n,
+            hash_kind: self.hash_kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_sharded_filter() {
+        let mut builder = BigsiBuilder::new(2500, 10, 3, 4, "shard_build_test");
+        builder.insert(0, "ATGT");
+        builder.insert(3, "ATGT");
+        builder.insert(7, "ATGT");
+        let bigsi = builder.finish();
+        assert_eq!(bigsi.get("ATGT").len(), 3 as usize);
+        assert_eq!(bigsi.get("ATGC").len(), 0 as usize);
+    }
+
+    #[test]
+    fn build_with_uneven_shard_count() {
+        // 5 buckets over 4 shards (shard_size = 2) leaves a trailing shard
+        // with nothing left to cover; it must come out length 0, not underflow.
+        let mut builder = BigsiBuilder::new(5, 10, 3, 4, "shard_build_test_uneven");
+        builder.insert(0, "ATGT");
+        let bigsi = builder.finish();
+        assert_eq!(bigsi.bigsi.len(), 5);
+    }
+}
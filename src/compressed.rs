@@ -0,0 +1,256 @@
+//! Golomb-Rice compressed on-disk serialization.
+//!
+//! `save`/`open` bincode-serialize the full `Vec<BitVec>`, which is wasteful once
+//! `slim` has run and most rows are sparse. This module stores, per accession
+//! column, the sorted list of bucket indices where that column is set,
+//! delta-encoded with Golomb-Rice coding. This typically shrinks a slimmed
+//! index by an order of magnitude while remaining losslessly reconstructable.
+
+use crate::{Bigsi, Error, HashKind};
+use bv::BitVec;
+use bv::*;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+/// Pick the Golomb-Rice parameter `P` for a column with `count` set bits out of `m` buckets.
+fn rice_param(m: usize, count: usize) -> u32 {
+    if count == 0 {
+        return 0;
+    }
+    let ratio = m as f64 / count as f64;
+    if ratio <= 1.0 {
+        0
+    } else {
+        ratio.log2().round() as u32
+    }
+}
+
+/// Growable, most-significant-bit-first bit writer used to pack the unary/binary codes.
+struct BitWriter {
+    bytes: Vec<u8>,
+    cur: u8,
+    nbits: u8,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter {
+            bytes: Vec::new(),
+            cur: 0,
+            nbits: 0,
+        }
+    }
+
+    fn push_bit(&mut self, bit: bool) {
+        self.cur = (self.cur << 1) | (bit as u8);
+        self.nbits += 1;
+        if self.nbits == 8 {
+            self.bytes.push(self.cur);
+            self.cur = 0;
+            self.nbits = 0;
+        }
+    }
+
+    fn push_unary(&mut self, q: u64) {
+        for _ in 0..q {
+            self.push_bit(true);
+        }
+        self.push_bit(false);
+    }
+
+    fn push_bits(&mut self, value: u64, width: u32) {
+        for i in (0..width).rev() {
+            self.push_bit((value >> i) & 1 == 1);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.cur <<= 8 - self.nbits;
+            self.bytes.push(self.cur);
+        }
+        self.bytes
+    }
+}
+
+/// Reads the bit stream produced by `BitWriter` back out.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    byte_pos: usize,
+    bit_pos: u8,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        BitReader {
+            bytes,
+            byte_pos: 0,
+            bit_pos: 0,
+        }
+    }
+
+    fn read_bit(&mut self) -> bool {
+        let byte = self.bytes[self.byte_pos];
+        let bit = (byte >> (7 - self.bit_pos)) & 1 == 1;
+        self.bit_pos += 1;
+        if self.bit_pos == 8 {
+            self.bit_pos = 0;
+            self.byte_pos += 1;
+        }
+        bit
+    }
+
+    fn read_unary(&mut self) -> u64 {
+        let mut q = 0u64;
+        while self.read_bit() {
+            q += 1;
+        }
+        q
+    }
+
+    fn read_bits(&mut self, width: u32) -> u64 {
+        let mut value = 0u64;
+        for _ in 0..width {
+            value = (value << 1) | (self.read_bit() as u64);
+        }
+        value
+    }
+}
+
+impl Bigsi {
+    /// Save the index to `file_name` using Golomb-Rice delta coding of the set
+    /// bucket indices in each accession column, instead of bincoding the raw
+    /// `Vec<BitVec>`. Pairs with [`Bigsi::read_compressed`].
+    pub fn save_compressed(&self, file_name: &str) {
+        let mut writer = BufWriter::new(File::create(file_name).unwrap());
+        let m = self.bigsi.len() as u64;
+        writer.write_all(&m.to_le_bytes()).unwrap();
+        writer.write_all(&self.num_hashes.to_le_bytes()).unwrap();
+        writer.write_all(&self.accessions.to_le_bytes()).unwrap();
+        writer.write_all(&[self.hash_kind.as_u8()]).unwrap();
+
+        for a in 0..self.accessions {
+            let mut positions = Vec::new();
+            for (i, row) in self.bigsi.iter().enumerate() {
+                if row.len() > 0 && row[a] {
+                    positions.push(i as u64);
+                }
+            }
+            let p = rice_param(m as usize, positions.len());
+            let mut bw = BitWriter::new();
+            let mut prev = 0u64;
+            for pos in &positions {
+                let d = pos - prev;
+                prev = *pos;
+                bw.push_unary(d >> p);
+                if p > 0 {
+                    bw.push_bits(d & ((1u64 << p) - 1), p);
+                }
+            }
+            let body = bw.finish();
+            writer
+                .write_all(&(positions.len() as u64).to_le_bytes())
+                .unwrap();
+            writer.write_all(&p.to_le_bytes()).unwrap();
+            writer.write_all(&(body.len() as u64).to_le_bytes()).unwrap();
+            writer.write_all(&body).unwrap();
+        }
+    }
+
+    /// Read an index previously written with [`Bigsi::save_compressed`],
+    /// reconstructing the in-memory `Vec<BitVec>` rows from the decoded
+    /// bucket indices. Returns [`Error::Io`] if the file cannot be opened and
+    /// [`Error::UnknownHashKind`] for an unrecognised hash-kind tag. A
+    /// truncated or otherwise corrupt file is not detected: reading it panics
+    /// or yields a wrong index.
+    pub fn read_compressed(&mut self, path: &str) -> Result<(), Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let m = read_u64(&mut reader) as usize;
+        let num_hashes = read_u64(&mut reader);
+        let accessions = read_u64(&mut reader);
+        let hash_kind_tag = read_u8(&mut reader);
+        let hash_kind =
+            HashKind::try_from_u8(hash_kind_tag).ok_or(Error::UnknownHashKind(hash_kind_tag))?;
+
+        let mut bigsi = vec![BitVec::new_fill(false, accessions); m];
+        for a in 0..accessions {
+            let count = read_u64(&mut reader) as usize;
+            let p = read_u32(&mut reader);
+            let body_len = read_u64(&mut reader) as usize;
+            let mut body = vec![0u8; body_len];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut br = BitReader::new(&body);
+            let mut pos = 0u64;
+            for _ in 0..count {
+                let q = br.read_unary();
+                let low = if p > 0 { br.read_bits(p) } else { 0 };
+                let d = (q << p) | low;
+                pos += d;
+                bigsi[pos as usize].set(a, true);
+            }
+        }
+
+        self.bigsi = bigsi;
+        self.num_hashes = num_hashes;
+        self.accessions = accessions;
+        self.hash_kind = hash_kind;
+        Ok(())
+    }
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> u64 {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf).unwrap();
+    u64::from_le_bytes(buf)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> u8 {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf).unwrap();
+    buf[0]
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> u32 {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf).unwrap();
+    u32::from_le_bytes(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_read_compressed_filter() {
+        let mut new_filter = Bigsi::new(250000, 10, 3);
+        new_filter.insert(0, "ATGT");
+        new_filter.insert(3, "ATGT");
+        new_filter.insert(7, "ATGT");
+        new_filter.slim();
+        new_filter.save_compressed("saved_compressed.bxi");
+
+        let mut read_filter = Bigsi::default();
+        read_filter.read_compressed("saved_compressed.bxi").unwrap();
+        assert_eq!(read_filter.get("ATGT").len(), 3 as usize);
+        assert_eq!(read_filter.get("ATGC").len(), 0 as usize);
+    }
+
+    #[test]
+    fn read_compressed_rejects_unknown_hash_kind() {
+        let new_filter = Bigsi::new(250000, 10, 3);
+        new_filter.save_compressed("saved_compressed_bad_hash_kind.bxi");
+
+        // hash_kind tag sits right after m/num_hashes/accessions (3 u64s).
+        let mut bytes = std::fs::read("saved_compressed_bad_hash_kind.bxi").unwrap();
+        bytes[24] = 255;
+        std::fs::write("saved_compressed_bad_hash_kind.bxi", &bytes).unwrap();
+
+        let mut read_filter = Bigsi::default();
+        match read_filter.read_compressed("saved_compressed_bad_hash_kind.bxi") {
+            Err(Error::UnknownHashKind(255)) => {}
+            other => panic!("expected UnknownHashKind(255), got {:?}", other),
+        }
+    }
+}
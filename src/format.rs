@@ -0,0 +1,187 @@
+//! Self-describing, versioned on-disk format for [`Bigsi::save`].
+//!
+//! Plain bincode trusts the stream blindly, so loading a truncated file, a
+//! file from an incompatible version, or one built with a different `m` or
+//! `eta` silently corrupts queries instead of failing loudly. This format
+//! prefixes the bincoded body with a magic tag, a format-version byte, and
+//! the parameters the body was built with (`m`, `num_hashes`, `accessions`,
+//! `hash_kind`, and a reserved flags byte), so [`Bigsi::open`]/[`Bigsi::load`]
+//! can validate the header and return a typed [`Error`] instead of panicking,
+//! and can dispatch between format versions as the format evolves.
+
+use crate::{Bigsi, HashKind};
+use bincode::{deserialize_from, serialize_into};
+use bv::BitVec;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+/// Identifies a `.bxi` file produced by this crate.
+const MAGIC: [u8; 4] = *b"BXI1";
+/// Current on-disk format version. Bump when the header or body layout changes.
+const FORMAT_VERSION: u8 = 1;
+
+/// Errors returned by [`Bigsi::open`] and [`Bigsi::load`].
+#[derive(Debug)]
+pub enum Error {
+    /// An I/O error occurred while reading the file.
+    Io(std::io::Error),
+    /// The file doesn't start with the expected magic bytes.
+    BadMagic,
+    /// The file declares a format version this build doesn't know how to read.
+    UnsupportedVersion(u8),
+    /// The bincoded body could not be deserialized.
+    Bincode(bincode::Error),
+    /// The header's declared `m` doesn't match the number of rows actually
+    /// deserialized from the body.
+    SizeMismatch { declared: u64, actual: usize },
+    /// The header's `hash_kind` byte doesn't match any known [`HashKind`].
+    UnknownHashKind(u8),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error reading index: {}", e),
+            Error::BadMagic => write!(f, "not a bigsi_rs index (bad magic bytes)"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported index format version: {}", v),
+            Error::Bincode(e) => write!(f, "could not deserialize index body: {}", e),
+            Error::SizeMismatch { declared, actual } => write!(
+                f,
+                "index header declares m={} but body has {} rows",
+                declared, actual
+            ),
+            Error::UnknownHashKind(tag) => write!(f, "unknown hash_kind tag: {}", tag),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Error {
+        Error::Bincode(e)
+    }
+}
+
+impl Bigsi {
+    /// Save the index to `file_name` as a versioned format: magic bytes, a
+    /// format-version byte, the index parameters, then the bincoded matrix.
+    pub fn save(&self, file_name: &str) {
+        let mut writer = BufWriter::new(File::create(file_name).unwrap());
+        writer.write_all(&MAGIC).unwrap();
+        writer.write_all(&[FORMAT_VERSION]).unwrap();
+        writer
+            .write_all(&(self.bigsi.len() as u64).to_le_bytes())
+            .unwrap();
+        writer.write_all(&self.num_hashes.to_le_bytes()).unwrap();
+        writer.write_all(&self.accessions.to_le_bytes()).unwrap();
+        writer.write_all(&[self.hash_kind.as_u8()]).unwrap();
+        writer.write_all(&[0u8]).unwrap(); // flags, reserved
+        serialize_into(&mut writer, &self.bigsi).unwrap();
+    }
+
+    /// Open `path`, validating the magic bytes and format version before
+    /// deserializing the matrix. Returns a typed [`Error`] instead of
+    /// panicking on a truncated, foreign, or incompatible-version file.
+    pub fn open(path: &str) -> Result<Bigsi, Error> {
+        let reader = BufReader::new(File::open(path)?);
+        Bigsi::load(reader)
+    }
+
+    /// Like [`Bigsi::open`], but reads from any `Read` implementation.
+    pub fn load<R: Read>(mut reader: R) -> Result<Bigsi, Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(Error::BadMagic);
+        }
+
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion(version[0]));
+        }
+
+        let m = read_u64(&mut reader)?;
+        let num_hashes = read_u64(&mut reader)?;
+        let accessions = read_u64(&mut reader)?;
+        let mut hash_kind_tag = [0u8; 1];
+        reader.read_exact(&mut hash_kind_tag)?;
+        let hash_kind = HashKind::try_from_u8(hash_kind_tag[0])
+            .ok_or(Error::UnknownHashKind(hash_kind_tag[0]))?;
+        let mut flags = [0u8; 1];
+        reader.read_exact(&mut flags)?;
+
+        let bigsi: Vec<BitVec> = deserialize_from(&mut reader)?;
+        if bigsi.len() as u64 != m {
+            return Err(Error::SizeMismatch {
+                declared: m,
+                actual: bigsi.len(),
+            });
+        }
+
+        Ok(Bigsi {
+            bigsi,
+            num_hashes,
+            accessions,
+            hash_kind,
+        })
+    }
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> std::io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_open_round_trips() {
+        let mut new_filter = Bigsi::new(250000, 10, 3);
+        new_filter.insert(0, "ATGT");
+        new_filter.insert(3, "ATGT");
+        new_filter.insert(7, "ATGT");
+        new_filter.save("saved_versioned.bxi");
+
+        let read_filter = Bigsi::open("saved_versioned.bxi").unwrap();
+        assert_eq!(read_filter.get("ATGT").len(), 3 as usize);
+        assert_eq!(read_filter.get("ATGC").len(), 0 as usize);
+    }
+
+    #[test]
+    fn open_rejects_bad_magic() {
+        std::fs::write("not_a_bigsi.bxi", b"not a real index").unwrap();
+        match Bigsi::open("not_a_bigsi.bxi") {
+            Err(Error::BadMagic) => {}
+            other => panic!("expected BadMagic, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn open_rejects_unknown_hash_kind() {
+        let new_filter = Bigsi::new(250000, 10, 3);
+        new_filter.save("saved_bad_hash_kind.bxi");
+
+        // Corrupt the hash_kind byte, which sits right after m/num_hashes/accessions.
+        let mut bytes = std::fs::read("saved_bad_hash_kind.bxi").unwrap();
+        let hash_kind_offset = MAGIC.len() + 1 + 8 + 8 + 8;
+        bytes[hash_kind_offset] = 255;
+        std::fs::write("saved_bad_hash_kind.bxi", &bytes).unwrap();
+
+        match Bigsi::open("saved_bad_hash_kind.bxi") {
+            Err(Error::UnknownHashKind(255)) => {}
+            other => panic!("expected UnknownHashKind(255), got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Pluggable hash backends for `Bigsi`.
+//!
+//! The hash was hardcoded to `fasthash::xx::hash64_with_seed`, with no record in
+//! the serialized index of which hash produced it -- a silent correctness hazard
+//! if the default ever changes. `HashKind` records the choice as a field on
+//! `Bigsi` itself, so a serialized index carries (and can be checked against)
+//! the hash it was built with.
+
+use std::convert::TryInto;
+
+/// Identifies which hash function a `Bigsi` index was built with.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum HashKind {
+    /// `fasthash`'s 64-bit xxHash. The original, back-compat default.
+    XxHash,
+    /// xxh3, faster than `XxHash` for bulk ingest.
+    Xxh3,
+    /// BLAKE3, for when adversarial inputs must be resisted.
+    Blake3,
+}
+
+impl Default for HashKind {
+    fn default() -> HashKind {
+        HashKind::XxHash
+    }
+}
+
+impl HashKind {
+    /// Hash `bytes` under `seed`, dispatching to the selected backend.
+    pub fn hash_with_seed(&self, bytes: &[u8], seed: u64) -> u64 {
+        match self {
+            HashKind::XxHash => fasthash::xx::hash64_with_seed(bytes, seed),
+            HashKind::Xxh3 => xxhash_rust::xxh3::xxh3_64_with_seed(bytes, seed),
+            HashKind::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                hasher.update(&seed.to_le_bytes());
+                hasher.update(bytes);
+                u64::from_le_bytes(hasher.finalize().as_bytes()[0..8].try_into().unwrap())
+            }
+        }
+    }
+
+    /// Compact tag used when persisting `HashKind` in a hand-rolled binary header.
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            HashKind::XxHash => 0,
+            HashKind::Xxh3 => 1,
+            HashKind::Blake3 => 2,
+        }
+    }
+
+    /// Fallible inverse of [`HashKind::as_u8`]. Returns `None` for a tag this
+    /// build doesn't recognize (a truncated, foreign, or corrupted file),
+    /// letting callers surface a typed error instead of panicking on
+    /// attacker- or corruption-controlled input.
+    pub fn try_from_u8(tag: u8) -> Option<HashKind> {
+        match tag {
+            0 => Some(HashKind::XxHash),
+            1 => Some(HashKind::Xxh3),
+            2 => Some(HashKind::Blake3),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bigsi;
+
+    #[test]
+    fn hash_kind_round_trips_through_tag() {
+        for kind in [HashKind::XxHash, HashKind::Xxh3, HashKind::Blake3] {
+            assert_eq!(HashKind::try_from_u8(kind.as_u8()), Some(kind));
+        }
+    }
+
+    #[test]
+    fn hash_kind_rejects_unknown_tag() {
+        assert_eq!(HashKind::try_from_u8(255), None);
+    }
+
+    #[test]
+    fn blake3_filter_finds_inserted_values() {
+        let mut new_filter = Bigsi::new_with_hash(250000, 10, 3, HashKind::Blake3);
+        new_filter.insert(0, "ATGT");
+        new_filter.insert(3, "ATGT");
+        new_filter.insert(7, "ATGT");
+        assert_eq!(new_filter.get("ATGT").len(), 3 as usize);
+        assert_eq!(new_filter.get("ATGC").len(), 0 as usize);
+    }
+}
@@ -30,14 +30,20 @@ extern crate bv;
 
 #[macro_use]
 extern crate serde_derive;
-use bincode::{deserialize_from, serialize};
 use bv::BitVec;
 use bv::BitsExt;
 use bv::*;
-use fasthash;
-use std::fs::File;
-use std::io::BufReader;
-use std::io::Write;
+
+mod builder;
+mod compressed;
+mod format;
+mod hash;
+mod mmap;
+
+pub use builder::BigsiBuilder;
+pub use format::Error;
+pub use hash::HashKind;
+pub use mmap::MmapBigsi;
 
 /// BIGSI-like data structure
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -45,16 +51,23 @@ pub struct Bigsi {
     pub bigsi: Vec<BitVec>, // vector of bitvecs
     pub num_hashes: u64,    // # of hashes needed
     pub accessions: u64,
+    pub hash_kind: HashKind, // which hash backend produced `bigsi`
 }
 
 //m: bigsi length, n: number of accessions, eta: num_hashes
 impl Bigsi {
-    /// Create a new index of size m, n aceesions and eta hashes.
+    /// Create a new index of size m, n aceesions and eta hashes, hashed with
+    /// the default backend ([`HashKind::XxHash`], for back-compat with existing indices).
     pub fn new(m: usize, n: u64, eta: u64) -> Bigsi {
+        Bigsi::new_with_hash(m, n, eta, HashKind::default())
+    }
+    /// Create a new index of size m, n accessions and eta hashes, hashed with `hash_kind`.
+    pub fn new_with_hash(m: usize, n: u64, eta: u64, hash_kind: HashKind) -> Bigsi {
         Bigsi {
             bigsi: vec![BitVec::new_fill(false, n); m],
             num_hashes: eta,
             accessions: n,
+            hash_kind,
         }
     }
     /// Create a new index with default parameters (size: 100, 2 hashes, 10 accessions).
@@ -63,14 +76,16 @@ impl Bigsi {
             bigsi: vec![BitVec::new_fill(false, 10); 1000],
             num_hashes: 2,
             accessions: 10,
+            hash_kind: HashKind::default(),
         }
     }
     /// Insert new value for an accession.
     pub fn insert(&mut self, accession: u64, value: &str) {
         // Generate a bit index for each of the hash functions needed
         for i in 0..self.num_hashes {
-            let bit_index = (fasthash::xx::hash64_with_seed(&value.as_bytes(), i as u64)
-                % self.bigsi.len() as u64) as usize;
+            let bit_index =
+                (self.hash_kind.hash_with_seed(&value.as_bytes(), i) % self.bigsi.len() as u64)
+                    as usize;
             self.bigsi[bit_index].set(accession, true);
         }
     }
@@ -93,7 +108,7 @@ impl Bigsi {
         let mut final_vec = BitVec::new_fill(true, self.accessions as u64);
         let mut hits = Vec::new();
         for i in 0..self.num_hashes {
-            let bit_index = (fasthash::xx::hash64_with_seed(&value.as_bytes(), i as u64)
+            let bit_index = (self.hash_kind.hash_with_seed(&value.as_bytes(), i)
                 % (self.bigsi.len() as u64)) as usize;
             if self.bigsi[bit_index].is_empty() {
                 return hits;
@@ -112,7 +127,7 @@ impl Bigsi {
     pub fn get_bv(&self, value: &str) -> BitVec {
         let mut final_vec = BitVec::new_fill(true, self.accessions as u64);
         for i in 0..self.num_hashes {
-            let bit_index = (fasthash::xx::hash64_with_seed(&value.as_bytes(), i as u64)
+            let bit_index = (self.hash_kind.hash_with_seed(&value.as_bytes(), i)
                 % (self.bigsi.len() as u64)) as usize;
             if self.bigsi[bit_index].is_empty() {
                 return self.bigsi[bit_index].to_owned();
@@ -122,11 +137,53 @@ impl Bigsi {
         }
         final_vec
     }
+    /// Score a whole set of query words against the index at once: for each
+    /// accession, the fraction of `words` whose hit bit vector ([`Bigsi::get_bv`])
+    /// includes that accession. Returns accessions whose fraction meets
+    /// `threshold`, sorted descending by fraction. An accession that can no
+    /// longer reach `threshold` with the words left to check is dropped early.
+    pub fn query_many(&self, words: &[&str], threshold: f64) -> Vec<(usize, f64)> {
+        let total = words.len();
+        let n = self.accessions as usize;
+        // Smallest hit count whose fraction passes the final filter below;
+        // `ceil(threshold * total)` can overshoot it through float rounding.
+        let min_hits = (0..=total as u64)
+            .find(|&c| c as f64 / total as f64 >= threshold)
+            .unwrap_or(total as u64 + 1);
+        let mut counts = vec![0u64; n];
+        let mut alive = vec![true; n];
+
+        for (processed, word) in words.iter().enumerate() {
+            let remaining = (total - processed - 1) as u64;
+            let bv = self.get_bv(word);
+            for a in 0..n {
+                if !alive[a] {
+                    continue;
+                }
+                if !bv.is_empty() && bv[a as u64] {
+                    counts[a] += 1;
+                }
+                if counts[a] + remaining < min_hits {
+                    alive[a] = false;
+                }
+            }
+        }
+
+        let mut hits: Vec<(usize, f64)> = counts
+            .into_iter()
+            .enumerate()
+            .map(|(a, count)| (a, count as f64 / total as f64))
+            .filter(|&(_, fraction)| fraction >= threshold)
+            .collect();
+        hits.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        hits
+    }
     ///concatenate two indices
     pub fn merge(&mut self, other_bigsi: &Bigsi) {
         //assert critical parameters are the same
         if (self.num_hashes != other_bigsi.num_hashes)
             || (self.bigsi.len() != other_bigsi.bigsi.len())
+            || (self.hash_kind != other_bigsi.hash_kind)
         {
             panic!("indices do not use the same parameters!");
         };
@@ -150,22 +207,6 @@ impl Bigsi {
             .collect();
         self.accessions = self.accessions + other_bigsi.accessions;
     }
-    /// Save index to file
-    pub fn save(&self, file_name: &str) {
-        let serialized: Vec<u8> = serialize(&self).unwrap();
-        let mut writer = File::create(file_name).unwrap();
-        writer
-            .write_all(&serialized)
-            .expect("problems preparing serialized data for writing");
-    }
-    /// Read index from file
-    pub fn read(&mut self, path: &str) {
-        let mut reader = BufReader::new(File::open(path).expect("Can't open index!"));
-        let bigsi: Bigsi = deserialize_from(&mut reader).expect("can't deserialize");
-        self.bigsi = bigsi.bigsi;
-        self.num_hashes = bigsi.num_hashes; // # of hashes needed
-        self.accessions = bigsi.accessions;
-    }
 }
 
 #[cfg(test)]
@@ -219,9 +260,22 @@ mod tests {
         new_filter.insert(3, "ATGT");
         new_filter.insert(7, "ATGT");
         new_filter.save("saved.bxi");
-        let mut read_filter = Bigsi::default();
-        read_filter.read("saved.bxi");
+        let read_filter = Bigsi::open("saved.bxi").unwrap();
         assert_eq!(read_filter.get("ATGT").len(), 3 as usize);
         assert_eq!(read_filter.get("ATGC").len(), 0 as usize);
     }
+    #[test]
+    fn query_many_scores_by_hit_fraction() {
+        let mut new_filter = Bigsi::new(250000, 10, 3);
+        new_filter.insert(0, "ATGT");
+        new_filter.insert(0, "ATGC");
+        new_filter.insert(3, "ATGT");
+
+        let hits = new_filter.query_many(&["ATGT", "ATGC"], 1.0);
+        assert_eq!(hits, vec![(0, 1.0)]);
+
+        let hits = new_filter.query_many(&["ATGT", "ATGC"], 0.5);
+        assert_eq!(hits[0], (0, 1.0));
+        assert!(hits.iter().any(|&(a, f)| a == 3 && f == 0.5));
+    }
 }
@@ -0,0 +1,184 @@
+//! Memory-mapped, read-only companion to [`Bigsi`].
+//!
+//! [`Bigsi::open`] deserializes the whole index into RAM, which is prohibitive for
+//! bacterial-pangenome-scale indices. [`MmapBigsi`] instead memory-maps a file
+//! laid out as a small header (`m`, `n`, `eta`, and a hash-kind tag byte), a
+//! row-offset/length table (rows are variable length because `slim` collapses
+//! empty rows to length zero), and a bit-packed body. `get`/`get_bv` seek
+//! straight to the rows a query touches, decode only those rows from the
+//! mapping, and AND them, so opening a multi-GB
+//! `.bxi` file is effectively instant and resident memory stays near zero.
+
+use crate::{Bigsi, Error, HashKind};
+use bv::BitVec;
+use bv::*;
+use memmap2::{Mmap, MmapOptions};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+const HEADER_LEN: usize = 25; // m, n, eta as u64, then a HashKind tag byte
+const OFFSET_ENTRY_LEN: usize = 16; // (offset: u64, len: u64) per row, in bytes
+
+fn bytes_per_row(n: u64) -> usize {
+    ((n + 7) / 8) as usize
+}
+
+impl Bigsi {
+    /// Write the index in the mmap-friendly layout consumed by [`MmapBigsi::open`]:
+    /// a header, a row offset/length table, and a bit-packed body.
+    pub fn save_mmap(&self, file_name: &str) {
+        let m = self.bigsi.len() as u64;
+        let row_bytes = bytes_per_row(self.accessions);
+
+        let mut offsets: Vec<(u64, u64)> = Vec::with_capacity(self.bigsi.len());
+        let mut body: Vec<u8> = Vec::new();
+        let mut cursor = 0u64;
+        for row in &self.bigsi {
+            if row.len() == 0 {
+                offsets.push((cursor, 0));
+                continue;
+            }
+            let mut packed = vec![0u8; row_bytes];
+            for a in 0..self.accessions {
+                if row[a] {
+                    packed[(a / 8) as usize] |= 1 << (7 - (a % 8));
+                }
+            }
+            offsets.push((cursor, row_bytes as u64));
+            body.extend_from_slice(&packed);
+            cursor += row_bytes as u64;
+        }
+
+        let mut writer = BufWriter::new(File::create(file_name).unwrap());
+        writer.write_all(&m.to_le_bytes()).unwrap();
+        writer.write_all(&self.accessions.to_le_bytes()).unwrap();
+        writer.write_all(&self.num_hashes.to_le_bytes()).unwrap();
+        writer.write_all(&[self.hash_kind.as_u8()]).unwrap();
+        for (offset, len) in &offsets {
+            writer.write_all(&offset.to_le_bytes()).unwrap();
+            writer.write_all(&len.to_le_bytes()).unwrap();
+        }
+        writer.write_all(&body).unwrap();
+    }
+}
+
+/// Read-only, memory-mapped view of a `.bxi` file written with [`Bigsi::save_mmap`].
+pub struct MmapBigsi {
+    mmap: Mmap,
+    pub m: u64,
+    pub n: u64,
+    pub eta: u64,
+    pub hash_kind: HashKind,
+    data_start: usize,
+}
+
+impl MmapBigsi {
+    /// Memory-map `path` for querying without loading the matrix into RAM.
+    /// Returns [`Error::Io`] if the file cannot be opened or mapped and
+    /// [`Error::UnknownHashKind`] for an unrecognised hash-kind tag. The file
+    /// carries no magic bytes and its length is not checked against the
+    /// header: a file shorter than the header panics here, and a truncated
+    /// table or body, or a foreign file, panics or gives wrong hits when queried.
+    pub fn open(path: &str) -> Result<MmapBigsi, Error> {
+        let file = File::open(path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file)? };
+
+        let m = u64::from_le_bytes(mmap[0..8].try_into().unwrap());
+        let n = u64::from_le_bytes(mmap[8..16].try_into().unwrap());
+        let eta = u64::from_le_bytes(mmap[16..24].try_into().unwrap());
+        let hash_kind =
+            HashKind::try_from_u8(mmap[24]).ok_or(Error::UnknownHashKind(mmap[24]))?;
+        let data_start = HEADER_LEN + (m as usize) * OFFSET_ENTRY_LEN;
+
+        Ok(MmapBigsi {
+            mmap,
+            m,
+            n,
+            eta,
+            hash_kind,
+            data_start,
+        })
+    }
+
+    fn offset_entry(&self, bit_index: usize) -> (usize, usize) {
+        let entry_start = HEADER_LEN + bit_index * OFFSET_ENTRY_LEN;
+        let offset = u64::from_le_bytes(
+            self.mmap[entry_start..entry_start + 8].try_into().unwrap(),
+        );
+        let len = u64::from_le_bytes(
+            self.mmap[entry_start + 8..entry_start + 16]
+                .try_into()
+                .unwrap(),
+        );
+        (self.data_start + offset as usize, len as usize)
+    }
+
+    fn row_bits(&self, bit_index: usize) -> BitVec {
+        let (start, len) = self.offset_entry(bit_index);
+        if len == 0 {
+            return BitVec::new();
+        }
+        let packed = &self.mmap[start..start + len];
+        let mut row = BitVec::new_fill(false, self.n);
+        for a in 0..self.n {
+            if packed[(a / 8) as usize] & (1 << (7 - (a % 8))) != 0 {
+                row.set(a, true);
+            }
+        }
+        row
+    }
+
+    /// Given a value, return hits as a bit vector, mirroring [`Bigsi::get_bv`].
+    pub fn get_bv(&self, value: &str) -> BitVec {
+        let mut final_vec = BitVec::new_fill(true, self.n);
+        for i in 0..self.eta {
+            let bit_index =
+                (self.hash_kind.hash_with_seed(&value.as_bytes(), i) % self.m) as usize;
+            let row = self.row_bits(bit_index);
+            if row.is_empty() {
+                return row;
+            }
+            for a in 0..self.n {
+                if !row[a] {
+                    final_vec.set(a, false);
+                }
+            }
+        }
+        final_vec
+    }
+
+    /// Given a value, return a vector with accessions containing the query value.
+    pub fn get(&self, value: &str) -> Vec<usize> {
+        let final_vec = self.get_bv(value);
+        let mut hits = Vec::new();
+        if final_vec.is_empty() {
+            return hits;
+        }
+        for item in 0..self.n {
+            if final_vec[item] {
+                hits.push(item as usize);
+            }
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_query_mmap_filter() {
+        let mut new_filter = Bigsi::new(250000, 10, 3);
+        new_filter.insert(0, "ATGT");
+        new_filter.insert(3, "ATGT");
+        new_filter.insert(7, "ATGT");
+        new_filter.slim();
+        new_filter.save_mmap("saved_mmap.bxi");
+
+        let mapped = MmapBigsi::open("saved_mmap.bxi").unwrap();
+        assert_eq!(mapped.get("ATGT").len(), 3 as usize);
+        assert_eq!(mapped.get("ATGC").len(), 0 as usize);
+    }
+}